@@ -2,10 +2,17 @@
 #[macro_use]
 extern crate quickcheck;
 extern crate unicode_categories;
+extern crate unicode_width;
 
 use std::borrow::Cow;
 use std::{char, str};
 use unicode_categories::UnicodeCategories;
+use unicode_width::UnicodeWidthChar;
+
+#[cfg(unix)]
+use std::ffi::{OsStr, OsString};
+#[cfg(unix)]
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
 
 /// Escape the provided string with shell-like quoting and escapes.
 /// Strings which do not need to be escaped will be returned unchanged.
@@ -32,67 +39,274 @@ use unicode_categories::UnicodeCategories;
 /// println!("{}", escape("String with spaces")); // single-quoteable
 /// // 'String with spaces'
 /// # assert_eq!(escape("String with spaces"), "'String with spaces'");
-/// println!("{}", escape("æ±æ–¹")); // no escapes needed
-/// // æ±æ–¹
-/// # assert_eq!(escape("æ±æ–¹"), "æ±æ–¹");
+/// println!("{}", escape("東方")); // no escapes needed
+/// // 東方
+/// # assert_eq!(escape("東方"), "東方");
 /// println!("{}", escape("\"new\nline\"")); // escape needed
 /// // "\"new\nline\""
 /// # assert_eq!(escape("\"new\nline\""), "\"\\\"new\\nline\\\"\"");
 /// ```
 // escape performs some minimal 'shell-like' escaping on a given string
-pub fn escape(s: &str) -> Cow<str> {
+pub fn escape(s: &str) -> Cow<'_, str> {
+    match quote_mode(s.as_bytes(), EscapeStyle::Default) {
+        QuoteMode::None => Cow::Borrowed(s),
+        _ => Cow::Owned(Quoted(s).to_string()),
+    }
+}
+
+/// A zero-allocation [Display](std::fmt::Display) adapter that streams the shell-escaped form of
+/// a string directly into a formatter, so that e.g. `println!("{}", path.quoted())` doesn't need
+/// to build an intermediate `String`. Use [Quotable::quoted](Quotable::quoted) to construct one.
+///
+/// [escape](escape) is implemented in terms of this adapter for callers who do want an owned
+/// value.
+pub struct Quoted<'a>(&'a str);
+
+impl<'a> std::fmt::Display for Quoted<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let style = EscapeStyle::Default;
+        write_quoted(
+            f,
+            self.0.as_bytes(),
+            quote_mode(self.0.as_bytes(), style),
+            style,
+        )
+    }
+}
+
+/// Extension trait adding a zero-allocation [quoted](Quotable::quoted) method to string types.
+///
+/// # Examples
+/// ```
+/// use snailquote::Quotable;
+/// assert_eq!(format!("{}", "spaces here".quoted()), "'spaces here'");
+/// ```
+pub trait Quotable {
+    /// Wrap `self` in a [Quoted](Quoted) adapter that streams its shell-escaped form directly
+    /// into a [Display](std::fmt::Display) formatter, without allocating.
+    fn quoted(&self) -> Quoted<'_>;
+}
+
+impl Quotable for str {
+    fn quoted(&self) -> Quoted<'_> {
+        Quoted(self)
+    }
+}
+
+// QuoteMode is the outcome of the decision pass shared by escape/Quoted: whether a string can be
+// emitted unchanged, wrapped in single quotes, or needs the full double-quote escaping logic.
+enum QuoteMode {
+    None,
+    Single,
+    Double,
+}
+
+// quote_mode scans `s` to decide how it needs to be quoted, without writing anything. This is the
+// first of the two passes behind escape's output; the second pass (performed by write_quoted)
+// does the actual writing. It works byte-wise (decoding via next_byte_char) so that it's shared
+// between str input (escape/Quoted/escape_with, where every decoded item is ByteChar::Utf8) and
+// byte-string input (escape_bytes, where invalid utf8 always forces double-quoting).
+fn quote_mode(s: &[u8], style: EscapeStyle) -> QuoteMode {
     let mut needs_quoting = false;
     let mut single_quotable = true;
 
-    for c in s.chars() {
-        if c == '\'' || c == '\\' {
-            single_quotable = false;
-            needs_quoting = true;
-        } else if c == '"' {
-            needs_quoting = true;
-        } else if c == ' ' {
-            // special case; whitespace that can be single quoted.
-            // Other whitespace (e.g. '\t') needs double-quoting escaping, but literal spaces only
-            // need quoting, not escaping.
-            needs_quoting = true;
-        } else if c.is_whitespace() || c.is_separator() || c.is_other() {
-            single_quotable = false;
-            needs_quoting = true;
+    let mut rest = s;
+    while !rest.is_empty() {
+        let (item, len) = next_byte_char(rest);
+        match item {
+            ByteChar::Utf8(c) => {
+                if c == '\'' || c == '\\' {
+                    single_quotable = false;
+                    needs_quoting = true;
+                } else if c == '"' {
+                    needs_quoting = true;
+                } else if c == ' ' {
+                    // special case; whitespace that can be single quoted.
+                    // Other whitespace (e.g. '\t') needs double-quoting escaping, but literal
+                    // spaces only need quoting, not escaping.
+                    needs_quoting = true;
+                } else if c.is_whitespace()
+                    || c.is_separator()
+                    || c.is_other()
+                    || forces_escape(style, c)
+                {
+                    single_quotable = false;
+                    needs_quoting = true;
+                }
+            }
+            ByteChar::Raw(_) => {
+                // Invalid utf8 always needs a `\xNN` escape, which only double quotes support.
+                single_quotable = false;
+                needs_quoting = true;
+            }
         }
         if needs_quoting && !single_quotable {
             // We know we'll need double quotes, no need to check further
             break;
         }
+        rest = &rest[len..];
     }
 
     if !needs_quoting {
-        return Cow::from(s);
+        QuoteMode::None
+    } else if single_quotable {
+        QuoteMode::Single
+    } else {
+        QuoteMode::Double
     }
-    if single_quotable {
-        return format!("'{}'", s).into();
-    }
-    // otherwise we need to double quote it
-
-    let mut output = String::with_capacity(s.len());
-    output.push('"');
-
-    for c in s.chars() {
-        if c == '"' {
-            output += "\\\"";
-        } else if c == '\\' {
-            output += "\\\\";
-        } else if c == ' ' {
-            // avoid 'escape_unicode' for ' ' even though it's a separator
-            output.push(c);
-        } else if c.is_other() || c.is_separator() {
-            output += &escape_character(c);
-        } else {
-            output.push(c);
+}
+
+// write_quoted performs the second pass of escape's two-pass algorithm: given the QuoteMode
+// quote_mode already decided on, it writes the quoted/escaped form of `s` into `w`. Bytes that
+// aren't part of a valid utf8 sequence are written as `\xNN`; since every other branch writes
+// plain ASCII or valid utf8, the result is always valid utf8, so `w` can be a plain
+// fmt::Write — shared by Quoted's Display impl (writing straight into a Formatter), escape_with
+// (writing into a String that's then wrapped in a Cow), and escape_bytes (writing into a String
+// that's then converted into the returned byte Cow).
+fn write_quoted(
+    w: &mut impl std::fmt::Write,
+    s: &[u8],
+    mode: QuoteMode,
+    style: EscapeStyle,
+) -> std::fmt::Result {
+    match mode {
+        QuoteMode::None => {
+            // QuoteMode::None is only ever produced for valid-utf8 input (invalid utf8 always
+            // forces at least double-quoting), so this is a lossless str reconstruction.
+            write!(
+                w,
+                "{}",
+                str::from_utf8(s).expect("QuoteMode::None implies valid utf8")
+            )
+        }
+        QuoteMode::Single => {
+            write!(
+                w,
+                "'{}'",
+                str::from_utf8(s).expect("QuoteMode::Single implies valid utf8")
+            )
+        }
+        QuoteMode::Double => {
+            write!(w, "\"")?;
+            let mut rest = s;
+            while !rest.is_empty() {
+                let (item, len) = next_byte_char(rest);
+                match item {
+                    ByteChar::Utf8(c) => {
+                        if c == '"' {
+                            write!(w, "\\\"")?;
+                        } else if c == '\\' {
+                            write!(w, "\\\\")?;
+                        } else if c == ' ' {
+                            // avoid 'escape_unicode' for ' ' even though it's a separator
+                            write!(w, " ")?;
+                        } else if c.is_other() || c.is_separator() || forces_escape(style, c) {
+                            // Same escapes as escape_character, written straight into `w`
+                            // instead of through a temporary String.
+                            match c {
+                                '\u{07}' => w.write_str("\\a")?,
+                                '\u{08}' => w.write_str("\\b")?,
+                                '\u{0b}' => w.write_str("\\v")?,
+                                '\u{0c}' => w.write_str("\\f")?,
+                                '\u{1b}' => w.write_str("\\e")?,
+                                c => write!(w, "{}", c.escape_default())?,
+                            }
+                        } else {
+                            write!(w, "{}", c)?;
+                        }
+                    }
+                    ByteChar::Raw(b) => {
+                        write!(w, "\\x{:02x}", b)?;
+                    }
+                }
+                rest = &rest[len..];
+            }
+            write!(w, "\"")
         }
     }
+}
 
-    output.push('"');
-    output.into()
+/// Controls how aggressively [escape_with](escape_with) treats printable-but-suspicious
+/// characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EscapeStyle {
+    /// The same behavior as [escape](escape): escape whitespace, separators, and other
+    /// unprintable/unusual characters.
+    Default,
+    /// In addition to `Default`'s rules, force-escape any character with zero display width
+    /// (per `unicode-width`) and any default-ignorable code point, including zero-width
+    /// spaces/joiners and bidi formatting controls (embeddings, overrides, isolates). This guards
+    /// against Trojan-Source-style attacks, where a hidden character makes a printed string (e.g.
+    /// in a log line) display differently than its actual bytes; some of these, such as a bare
+    /// combining mark, pass through `escape`'s default rules unquoted.
+    ForceVisible,
+}
+
+/// Escape the provided string the same way [escape](escape) does, but with the option to also
+/// force-escape printable-but-dangerous characters that [escape](escape) leaves alone.
+///
+/// See [EscapeStyle](EscapeStyle) for the available styles. The result round-trips through
+/// [unescape](unescape) exactly like [escape](escape)'s does.
+///
+/// # Examples
+/// ```
+/// use snailquote::{escape_with, EscapeStyle};
+/// assert_eq!(escape_with("foo", EscapeStyle::ForceVisible), "foo");
+/// assert_eq!(
+///     escape_with("foo\u{200b}bar", EscapeStyle::ForceVisible),
+///     "\"foo\\u{200b}bar\""
+/// );
+/// ```
+pub fn escape_with(s: &str, style: EscapeStyle) -> Cow<'_, str> {
+    let mode = quote_mode(s.as_bytes(), style);
+    if let QuoteMode::None = mode {
+        return Cow::Borrowed(s);
+    }
+    let mut output = String::with_capacity(s.len() + 2);
+    write_quoted(&mut output, s.as_bytes(), mode, style).expect("writing to a String cannot fail");
+    Cow::Owned(output)
+}
+
+// forces_escape reports whether `style` requires `c` to always be escaped, even if it's
+// otherwise printable: any code point unicode-width reports as zero display width (or doesn't
+// recognize at all), or any Default_Ignorable_Code_Point (some of which, like the soft hyphen or
+// Hangul fillers, have a non-zero width), the kind of characters used in Trojan-Source-style
+// attacks.
+fn forces_escape(style: EscapeStyle, c: char) -> bool {
+    if style != EscapeStyle::ForceVisible || c == ' ' {
+        return false;
+    }
+    let default_ignorable = matches!(c,
+        '\u{ad}' | '\u{34f}' | '\u{61c}' | '\u{115f}'..='\u{1160}' | '\u{17b4}'..='\u{17b5}'
+        | '\u{180b}'..='\u{180f}' | '\u{200b}'..='\u{200f}' | '\u{202a}'..='\u{202e}'
+        | '\u{2060}'..='\u{206f}' | '\u{3164}' | '\u{fe00}'..='\u{fe0f}' | '\u{feff}'
+        | '\u{ffa0}' | '\u{fff0}'..='\u{fff8}' | '\u{1bca0}'..='\u{1bca3}'
+        | '\u{1d173}'..='\u{1d17a}' | '\u{e0000}'..='\u{e0fff}');
+    default_ignorable || c.width().map(|w| w == 0).unwrap_or(true)
+}
+
+/// Escape the provided byte-string with shell-like quoting and escapes.
+///
+/// This is the byte-oriented counterpart to [escape](escape), useful for escaping data that
+/// isn't necessarily valid UTF-8, such as filenames or argv captured from the kernel. Bytes
+/// which aren't part of a valid UTF-8 sequence are emitted as `\xNN` hex escapes; everything
+/// else uses the same rules as [escape](escape).
+///
+/// # Examples
+/// ```
+/// use snailquote::escape_bytes;
+/// assert_eq!(escape_bytes(b"foo"), &b"foo"[..]);
+/// assert_eq!(escape_bytes(b"\xffoo"), &b"\"\\xffoo\""[..]);
+/// ```
+pub fn escape_bytes(s: &[u8]) -> Cow<'_, [u8]> {
+    let mode = quote_mode(s, EscapeStyle::Default);
+    if let QuoteMode::None = mode {
+        return Cow::from(s);
+    }
+    let mut output = String::with_capacity(s.len() + 2);
+    write_quoted(&mut output, s, mode, EscapeStyle::Default)
+        .expect("writing to a String cannot fail");
+    output.into_bytes().into()
 }
 
 // escape_character is an internal helper method which converts the given unicode character into an
@@ -116,6 +330,28 @@ fn escape_character(c: char) -> String {
     }
 }
 
+// ByteChar is the result of decoding a single unit from a byte-string: either a full unicode
+// scalar value if the bytes formed a valid utf8 sequence, or a single raw byte if they didn't.
+enum ByteChar {
+    Utf8(char),
+    Raw(u8),
+}
+
+// next_byte_char decodes the first unicode scalar value at the start of `bytes`, falling back to
+// a single raw byte if `bytes` doesn't start with valid utf8. It returns the decoded item along
+// with the number of bytes it consumed.
+fn next_byte_char(bytes: &[u8]) -> (ByteChar, usize) {
+    let max = bytes.len().min(4);
+    for len in 1..=max {
+        if let Ok(s) = str::from_utf8(&bytes[..len]) {
+            if let Some(c) = s.chars().next() {
+                return (ByteChar::Utf8(c), len);
+            }
+        }
+    }
+    (ByteChar::Raw(bytes[0]), 1)
+}
+
 /// Parse the provided shell-like quoted string, such as one produced by [escape](escape).
 ///
 /// # Details
@@ -145,12 +381,13 @@ fn escape_character(c: char) -> String {
 /// | \\     | \u{5C}  | Backslash |
 /// | \'     | \u{27}  | Single quote |
 /// | \"     | \u{22}  | Double quote |
+/// | \xXX   |         | Raw byte with hex code XX |
 /// | \u{XX} | \u{XX}  | Unicode character with hex code XX |
 ///
 /// # Errors
 ///
-/// The returned result will contain a human readable error if the string cannot be parsed as a
-/// valid quoted string.
+/// The returned result will contain an [UnescapeError](UnescapeError) if the string cannot be
+/// parsed as a valid quoted string.
 ///
 /// # Examples
 /// ```
@@ -170,111 +407,461 @@ fn escape_character(c: char) -> String {
 /// // line
 /// # assert_eq!(unescape("\"new\\nline\"").unwrap(), "new\nline");
 /// println!("{}", unescape("'some spaces'_some_unquoted_\"and a \\t tab\"").unwrap());
-/// // some spaces_some_unquoted_and a 	 tab
+/// // some spaces_some_unquoted_and a <TAB> tab
 /// # assert_eq!(unescape("'some spaces'_some_unquoted_\"and a \\t tab\"").unwrap(), "some spaces_some_unquoted_and a \t tab");
 /// ```
-// TODO: more proper error type
-pub fn unescape(s: &str) -> Result<String, String> {
+pub fn unescape(s: &str) -> Result<String, UnescapeError> {
+    let bytes = unescape_bytes(s.as_bytes())?;
+    String::from_utf8(bytes).map_err(|e| UnescapeError::InvalidUtf8 {
+        offset: e.utf8_error().valid_up_to(),
+    })
+}
+
+/// An error produced by [unescape](unescape) or [unescape_bytes](unescape_bytes) when the input
+/// is not a validly quoted string.
+///
+/// Every variant carries a byte offset at which the problem was found, so callers can underline
+/// the exact span, much like a compiler would. Because of this, `Display`'s messages are
+/// compiler-style (kind + byte offset) rather than reproducing the pre-`UnescapeError` messages
+/// verbatim, which embedded a copy of the whole input string; doing that here would mean owning a
+/// copy of the source in every variant, which this structured, byte-offset-based design is meant
+/// to avoid.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UnescapeError {
+    /// A `'` or `"` quote was opened but never closed before the end of the input.
+    UnterminatedQuote { offset: usize },
+    /// A `\` inside double quotes appeared as the last byte of the input, with no escape
+    /// character following it.
+    UnterminatedEscape { offset: usize },
+    /// A `\` was followed by a character that isn't a recognized escape.
+    InvalidEscape { offset: usize, char: char },
+    /// A `\x` or `\u{...}` escape's digits couldn't be parsed as hex.
+    InvalidUnicodeEscape { offset: usize, digits: String },
+    /// A `\u{...}` escape decoded to a value that isn't a valid unicode scalar value.
+    UnicodeOutOfRange { offset: usize, value: u32 },
+    /// A `\u` escape wasn't followed by an opening `{`.
+    MissingOpeningBrace { offset: usize },
+    /// A `\xNN` escape (or a run of them) decoded to bytes that aren't valid UTF-8, so the result
+    /// can't be returned as a `String`. Only reachable from [unescape](unescape), never from
+    /// [unescape_bytes](unescape_bytes). `offset` is a byte offset into the *unescaped* output,
+    /// not the original input, since the invalid bytes don't necessarily appear literally in the
+    /// source.
+    InvalidUtf8 { offset: usize },
+}
+
+impl UnescapeError {
+    // Shifts this error's offset by `delta`, so that an error produced while unescaping a slice
+    // of some larger string (e.g. one word of a `split` line) can be reported relative to the
+    // larger string instead of the slice.
+    fn offset_by(self, delta: usize) -> Self {
+        match self {
+            UnescapeError::UnterminatedQuote { offset } => UnescapeError::UnterminatedQuote {
+                offset: offset + delta,
+            },
+            UnescapeError::UnterminatedEscape { offset } => UnescapeError::UnterminatedEscape {
+                offset: offset + delta,
+            },
+            UnescapeError::InvalidEscape { offset, char } => UnescapeError::InvalidEscape {
+                offset: offset + delta,
+                char,
+            },
+            UnescapeError::InvalidUnicodeEscape { offset, digits } => {
+                UnescapeError::InvalidUnicodeEscape {
+                    offset: offset + delta,
+                    digits,
+                }
+            }
+            UnescapeError::UnicodeOutOfRange { offset, value } => {
+                UnescapeError::UnicodeOutOfRange {
+                    offset: offset + delta,
+                    value,
+                }
+            }
+            UnescapeError::MissingOpeningBrace { offset } => UnescapeError::MissingOpeningBrace {
+                offset: offset + delta,
+            },
+            UnescapeError::InvalidUtf8 { offset } => UnescapeError::InvalidUtf8 {
+                offset: offset + delta,
+            },
+        }
+    }
+}
+
+impl std::fmt::Display for UnescapeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            UnescapeError::UnterminatedQuote { offset } => {
+                write!(f, "unterminated quote starting at byte {}", offset)
+            }
+            UnescapeError::UnterminatedEscape { offset } => {
+                write!(f, "invalid escape at byte {}", offset)
+            }
+            UnescapeError::InvalidEscape { offset, char } => {
+                write!(f, "invalid escape \\{} at byte {}", char, offset)
+            }
+            UnescapeError::InvalidUnicodeEscape { offset, digits } => {
+                write!(f, "could not parse {:?} as hex at byte {}", digits, offset)
+            }
+            UnescapeError::UnicodeOutOfRange { offset, value } => write!(
+                f,
+                "{:#x} is not a valid unicode char, at byte {}",
+                value, offset
+            ),
+            UnescapeError::MissingOpeningBrace { offset } => {
+                write!(
+                    f,
+                    "expected '{{' character in unicode escape at byte {}",
+                    offset
+                )
+            }
+            UnescapeError::InvalidUtf8 { offset } => {
+                write!(f, "unescaped bytes are not valid utf-8 at byte {}", offset)
+            }
+        }
+    }
+}
+
+impl std::error::Error for UnescapeError {}
+
+/// Parse the provided shell-like quoted byte-string, such as one produced by
+/// [escape_bytes](escape_bytes).
+///
+/// This is the byte-oriented counterpart to [unescape](unescape); it understands the same
+/// escapes (see the table on [unescape](unescape)), plus the `\xNN` raw byte escape, which lets
+/// the result contain bytes that aren't valid UTF-8.
+///
+/// # Examples
+/// ```
+/// use snailquote::unescape_bytes;
+/// assert_eq!(unescape_bytes(b"\"\\xff\"").unwrap(), vec![0xff]);
+/// ```
+pub fn unescape_bytes(s: &[u8]) -> Result<Vec<u8>, UnescapeError> {
     let mut in_single_quote = false;
     let mut in_double_quote = false;
+    let mut quote_offset = 0;
 
-    let mut chars = s.chars().enumerate();
+    let mut bytes = s.iter().enumerate();
 
-    let mut res = String::with_capacity(s.len());
+    let mut res = Vec::with_capacity(s.len());
 
-    while let Some((idx, c)) = chars.next() {
+    while let Some((idx, &b)) = bytes.next() {
         // when in a single quote, no escapes are possible
         if in_single_quote {
-            if c == '\'' {
+            if b == b'\'' {
                 in_single_quote = false;
                 continue;
             }
         } else if in_double_quote {
-            if c == '"' {
+            if b == b'"' {
                 in_double_quote = false;
                 continue;
             }
 
-            if c == '\\' {
-                match chars.next() {
+            if b == b'\\' {
+                match bytes.next() {
                     None => {
-                        return Err(format!("invalid escape at char {} in string {}", idx, s));
+                        return Err(UnescapeError::UnterminatedEscape { offset: idx });
                     }
-                    Some((idx, c2)) => {
-                        res.push(match c2 {
-                            'a' => '\u{07}',
-                            'b' => '\u{08}',
-                            'v' => '\u{0B}',
-                            'f' => '\u{0C}',
-                            'n' => '\n',
-                            'r' => '\r',
-                            't' => '\t',
-                            'e' | 'E' => '\u{1B}',
-                            '\\' => '\\',
-                            '\'' => '\'',
-                            '"' => '"',
-                            ' ' => ' ',
-                            'u' => match parse_unicode(&mut chars) {
-                                Ok(c) => c,
-                                Err(e) => {
-                                    return Err(format!(
-                                        "\\u could not be parsed at {} in {}: {}",
-                                        idx, s, e
-                                    ));
-                                }
-                            },
+                    Some((idx2, &b2)) => {
+                        match b2 {
+                            b'a' => res.push(0x07),
+                            b'b' => res.push(0x08),
+                            b'v' => res.push(0x0B),
+                            b'f' => res.push(0x0C),
+                            b'n' => res.push(b'\n'),
+                            b'r' => res.push(b'\r'),
+                            b't' => res.push(b'\t'),
+                            b'e' | b'E' => res.push(0x1B),
+                            b'\\' => res.push(b'\\'),
+                            b'\'' => res.push(b'\''),
+                            b'"' => res.push(b'"'),
+                            b' ' => res.push(b' '),
+                            b'x' => res.push(parse_hex_byte(idx, &mut bytes)?),
+                            b'u' => {
+                                let c = parse_unicode(idx, &mut bytes)?;
+                                let mut buf = [0; 4];
+                                res.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+                            }
                             _ => {
-                                return Err(format!(
-                                    "invalid escape {}{} at {} in {}",
-                                    c, c2, idx, s
-                                ));
+                                // The escape character itself might be multi-byte utf8 (or, if
+                                // the input isn't valid utf8 there either, not decodable at all);
+                                // decode valid utf8 properly instead of casting its first byte to
+                                // char, which would mangle anything non-ASCII. An undecodable
+                                // byte is reported as the char with that byte's value.
+                                let (item, len) = next_byte_char(&s[idx2..]);
+                                let c = match item {
+                                    ByteChar::Utf8(c) => c,
+                                    ByteChar::Raw(b) => b as char,
+                                };
+                                for _ in 1..len {
+                                    bytes.next();
+                                }
+                                return Err(UnescapeError::InvalidEscape {
+                                    offset: idx,
+                                    char: c,
+                                });
                             }
-                        });
+                        };
                         continue;
                     }
                 };
             }
-        } else if c == '\'' {
+        } else if b == b'\'' {
             in_single_quote = true;
+            quote_offset = idx;
             continue;
-        } else if c == '"' {
+        } else if b == b'"' {
             in_double_quote = true;
+            quote_offset = idx;
             continue;
         }
 
-        res.push(c);
+        res.push(b);
+    }
+
+    if in_single_quote || in_double_quote {
+        return Err(UnescapeError::UnterminatedQuote {
+            offset: quote_offset,
+        });
     }
 
     Ok(res)
 }
 
-// parse_unicode takes an iterator over characters and attempts to extract a single unicode
+// parse_hex_byte takes an iterator over indexed bytes and attempts to extract exactly two hex
+// digits from it, returning the raw byte they encode. The cursor is expected to already be
+// advanced past the 'x' in a '\xNN' escape. `escape_offset` is the byte offset of the '\' that
+// introduced the escape, used to build a useful error.
+fn parse_hex_byte<'a, I>(escape_offset: usize, bytes: &mut I) -> Result<u8, UnescapeError>
+where
+    I: Iterator<Item = (usize, &'a u8)>,
+{
+    let digits: Vec<u8> = bytes.take(2).map(|(_, &b)| b).collect();
+    let invalid = || UnescapeError::InvalidUnicodeEscape {
+        offset: escape_offset,
+        digits: String::from_utf8_lossy(&digits).into_owned(),
+    };
+    if digits.len() != 2 || !digits.iter().all(u8::is_ascii_hexdigit) {
+        return Err(invalid());
+    }
+    let digits_str = str::from_utf8(&digits).map_err(|_| invalid())?;
+    u8::from_str_radix(digits_str, 16).map_err(|_| invalid())
+}
+
+// parse_unicode takes an iterator over indexed bytes and attempts to extract a single unicode
 // character from it.
 // It parses escapes of the form '\u{65b9}', but this internal helper function expects the cursor
-// to be advanced to between the 'u' and '{'.
-// It also expects to be passed an iterator which includes the index for the purpose of advancing
-// it  as well, such as is produced by enumerate.
-fn parse_unicode<I>(chars: &mut I) -> Result<char, String>
+// to be advanced to between the 'u' and '{'. `escape_offset` is the byte offset of the '\' that
+// introduced the escape, used to build a useful error.
+fn parse_unicode<'a, I>(escape_offset: usize, bytes: &mut I) -> Result<char, UnescapeError>
 where
-    I: Iterator<Item = (usize, char)>,
+    I: Iterator<Item = (usize, &'a u8)>,
 {
-    match chars.next() {
-        Some((_, '{')) => {}
+    match bytes.next() {
+        Some((_, b'{')) => {}
         _ => {
-            return Err("expected '{{' character in unicode escape".to_string());
+            return Err(UnescapeError::MissingOpeningBrace {
+                offset: escape_offset,
+            });
         }
     }
 
-    let unicode_seq: String = chars
-        .take_while(|&(_, c)| c != '}')
-        .map(|(_, c)| c)
+    let unicode_seq: Vec<u8> = bytes
+        .take_while(|&(_, &b)| b != b'}')
+        .map(|(_, &b)| b)
         .collect();
+    let invalid = || UnescapeError::InvalidUnicodeEscape {
+        offset: escape_offset,
+        digits: String::from_utf8_lossy(&unicode_seq).into_owned(),
+    };
+    let unicode_seq_str = str::from_utf8(&unicode_seq).map_err(|_| invalid())?;
+
+    let value = u32::from_str_radix(unicode_seq_str, 16).map_err(|_| invalid())?;
+    char::from_u32(value).ok_or(UnescapeError::UnicodeOutOfRange {
+        offset: escape_offset,
+        value,
+    })
+}
 
-    u32::from_str_radix(&unicode_seq, 16)
-        .map_err(|e| format!("could not parse {} as u32 hex: {}", unicode_seq, e))
-        .and_then(|u| {
-            char::from_u32(u).ok_or_else(|| format!("could not parse {} as a unicode char", u))
+/// Split a whole command line into separate unescaped words, using POSIX-ish shell quoting
+/// rules.
+///
+/// Unlike [unescape](unescape), which collapses an entire string into a single value, `split`
+/// treats unquoted whitespace as a word boundary: `'a b' c` becomes `["a b", "c"]` rather than
+/// the single string `"a b c"` you'd get from unescaping the whole line as one value. Quoting and
+/// escaping within each word follow exactly the same rules as [unescape](unescape); [join](join)
+/// is the inverse operation.
+///
+/// # Errors
+///
+/// Returns an [UnescapeError](UnescapeError) if any word fails to unescape, including
+/// [UnescapeError::UnterminatedQuote](UnescapeError::UnterminatedQuote) if a quote is left open
+/// at the end of the line.
+///
+/// # Examples
+/// ```
+/// use snailquote::split;
+/// assert_eq!(split("'a b' c").unwrap(), vec!["a b".to_string(), "c".to_string()]);
+/// ```
+pub fn split(s: &str) -> Result<Vec<String>, UnescapeError> {
+    split_words(s)?
+        .into_iter()
+        .map(|(word, word_start)| unescape(word).map_err(|e| e.offset_by(word_start)))
+        .collect()
+}
+
+/// Join a sequence of words into a single shell-escaped command line.
+///
+/// Each word is escaped independently with [escape](escape), so that a word's own embedded
+/// spaces are preserved as a single token, then the escaped words are space-joined. This is the
+/// inverse of [split](split).
+///
+/// An empty word is force-quoted as `''`: [escape](escape) leaves `""` unchanged since it needs
+/// no quoting on its own, but an unquoted empty word contributes nothing between the spaces
+/// around it and would silently vanish as a positional argument once [split](split) sees it
+/// again.
+///
+/// # Examples
+/// ```
+/// use snailquote::join;
+/// assert_eq!(join(vec!["a b", "c"]), "'a b' c");
+/// assert_eq!(join(vec!["a", "", "b"]), "a '' b");
+/// ```
+pub fn join<'a, I: IntoIterator<Item = &'a str>>(words: I) -> String {
+    words
+        .into_iter()
+        .map(|w| {
+            if w.is_empty() {
+                "''".to_string()
+            } else {
+                escape(w).into_owned()
+            }
         })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+// SplitState tracks which part of a word `split_words` is currently scanning, mirroring the
+// states unescape_bytes itself moves through, so that quoted whitespace and escaped quote
+// characters aren't mistaken for word boundaries.
+enum SplitState {
+    Delimiter,
+    Unquoted,
+    SingleQuoted,
+    DoubleQuoted,
+    DoubleQuotedEscape,
+}
+
+// split_words slices `s` into whitespace-delimited words (along with each word's starting byte
+// offset in `s`, so callers can translate errors from unescaping a word back into offsets into
+// `s`) without interpreting any escapes, leaving that to unescape. A word may mix multiple
+// quoted/unquoted runs, e.g. `'a b'c` is a single word.
+fn split_words(s: &str) -> Result<Vec<(&str, usize)>, UnescapeError> {
+    let mut words = Vec::new();
+    let mut state = SplitState::Delimiter;
+    let mut word_start = 0;
+    let mut quote_offset = 0;
+
+    for (idx, c) in s.char_indices() {
+        match state {
+            SplitState::Delimiter => {
+                if c.is_whitespace() {
+                    continue;
+                }
+                word_start = idx;
+                state = match c {
+                    '\'' => {
+                        quote_offset = idx;
+                        SplitState::SingleQuoted
+                    }
+                    '"' => {
+                        quote_offset = idx;
+                        SplitState::DoubleQuoted
+                    }
+                    _ => SplitState::Unquoted,
+                };
+            }
+            SplitState::Unquoted => {
+                if c.is_whitespace() {
+                    words.push((&s[word_start..idx], word_start));
+                    state = SplitState::Delimiter;
+                } else if c == '\'' {
+                    quote_offset = idx;
+                    state = SplitState::SingleQuoted;
+                } else if c == '"' {
+                    quote_offset = idx;
+                    state = SplitState::DoubleQuoted;
+                }
+            }
+            SplitState::SingleQuoted => {
+                if c == '\'' {
+                    state = SplitState::Unquoted;
+                }
+            }
+            SplitState::DoubleQuoted => {
+                if c == '"' {
+                    state = SplitState::Unquoted;
+                } else if c == '\\' {
+                    state = SplitState::DoubleQuotedEscape;
+                }
+            }
+            SplitState::DoubleQuotedEscape => {
+                // Whatever character follows a backslash in a double-quoted run is consumed as
+                // part of the escape; the escape table itself lives in unescape, this only needs
+                // to avoid treating an escaped `"` as the end of the quoted run.
+                state = SplitState::DoubleQuoted;
+            }
+        }
+    }
+
+    match state {
+        SplitState::Delimiter => {}
+        SplitState::Unquoted => words.push((&s[word_start..], word_start)),
+        SplitState::SingleQuoted | SplitState::DoubleQuoted | SplitState::DoubleQuotedEscape => {
+            return Err(UnescapeError::UnterminatedQuote {
+                offset: quote_offset,
+            });
+        }
+    }
+
+    Ok(words)
+}
+
+/// Escape the provided `OsStr` with shell-like quoting and escapes.
+///
+/// This mirrors [escape_bytes](escape_bytes), but works with `OsStr`/`OsString` so that values
+/// like filenames or `argv` entries, which aren't guaranteed to be valid UTF-8, can be escaped
+/// without a lossy conversion. Only available on unix, where `OsStr` is a thin wrapper over
+/// bytes.
+///
+/// # Examples
+/// ```
+/// use std::ffi::OsStr;
+/// use snailquote::escape_os_str;
+/// assert_eq!(escape_os_str(OsStr::new("foo")), OsStr::new("foo"));
+/// ```
+#[cfg(unix)]
+pub fn escape_os_str(s: &OsStr) -> Cow<'_, OsStr> {
+    match escape_bytes(s.as_bytes()) {
+        Cow::Borrowed(b) => Cow::Borrowed(OsStr::from_bytes(b)),
+        Cow::Owned(v) => Cow::Owned(OsString::from_vec(v)),
+    }
+}
+
+/// Parse the provided shell-like quoted `OsStr`, such as one produced by
+/// [escape_os_str](escape_os_str).
+///
+/// Only available on unix, where `OsStr` is a thin wrapper over bytes.
+///
+/// # Examples
+/// ```
+/// use std::ffi::OsStr;
+/// use snailquote::unescape_os_str;
+/// assert_eq!(unescape_os_str(OsStr::new("'foo bar'")).unwrap(), OsStr::new("foo bar"));
+/// ```
+#[cfg(unix)]
+pub fn unescape_os_str(s: &OsStr) -> Result<OsString, UnescapeError> {
+    unescape_bytes(s.as_bytes()).map(OsString::from_vec)
 }
 
 #[cfg(test)]
@@ -284,12 +871,12 @@ mod test {
     #[test]
     fn test_escape() {
         let test_cases = vec![
-            ("æ±æ–¹", "æ±æ–¹"),
+            ("東方", "東方"),
             ("\"'", r#""\"'""#),
             ("\\", "\"\\\\\""),
             ("spaces only", "'spaces only'"),
             ("some\ttabs", "\"some\\ttabs\""),
-            ("ðŸ’©", "ðŸ’©"),
+            ("💩", "💩"),
             ("\u{202e}RTL", "\"\\u{202e}RTL\""),
             ("no\u{202b}space", "\"no\\u{202b}space\""),
             (
@@ -303,10 +890,66 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_escape_bytes_invalid_utf8() {
+        assert_eq!(escape_bytes(b"\xff"), &b"\"\\xff\""[..]);
+        assert_eq!(escape_bytes(b"foo\xffbar"), &b"\"foo\\xffbar\""[..]);
+        assert_eq!(escape_bytes(b"foo"), &b"foo"[..]);
+    }
+
+    #[test]
+    fn test_quoted() {
+        let test_cases = vec![
+            ("foo", "foo"),
+            ("String with spaces", "'String with spaces'"),
+            ("\"new\nline\"", "\"\\\"new\\nline\\\"\""),
+        ];
+
+        for (s, expected) in test_cases {
+            assert_eq!(s.quoted().to_string(), expected);
+            assert_eq!(s.quoted().to_string(), escape(s));
+        }
+    }
+
+    #[test]
+    fn test_escape_with_force_visible() {
+        let test_cases = vec![
+            ("foo", "foo"),
+            ("foo\u{200b}bar", "\"foo\\u{200b}bar\""),
+            ("no\u{202b}space", "\"no\\u{202b}space\""),
+            // soft hyphen: default-ignorable, but non-zero display width
+            ("soft\u{ad}hyphen", "\"soft\\u{ad}hyphen\""),
+            // combining acute accent (following 'e'): zero display width, but not itself
+            // `is_other`/`is_separator`, so Default leaves it alone and only ForceVisible escapes
+            // it; this is the case the style is actually meant to add over Default.
+            ("e\u{301}", "\"e\\u{301}\""),
+        ];
+
+        for (s, expected) in test_cases {
+            assert_eq!(escape_with(s, EscapeStyle::ForceVisible), expected);
+            assert_eq!(
+                unescape(&escape_with(s, EscapeStyle::ForceVisible)).unwrap(),
+                s
+            );
+        }
+
+        // Default style is unaffected by the zero-width character.
+        assert_eq!(
+            escape_with("foo\u{200b}bar", EscapeStyle::Default),
+            escape("foo\u{200b}bar")
+        );
+
+        // Default leaves a bare combining mark alone entirely (it's neither is_other nor
+        // is_separator), so this is the case that actually distinguishes ForceVisible from
+        // Default, unlike the other cases above which Default already escapes on its own.
+        assert_eq!(escape_with("e\u{301}", EscapeStyle::Default), "e\u{301}");
+        assert_eq!(escape("e\u{301}"), "e\u{301}");
+    }
+
     #[test]
     fn test_unescape() {
-        assert_eq!(unescape("\"\\u{6771}\\u{65b9}\""), Ok("æ±æ–¹".to_string()));
-        assert_eq!(unescape("æ±æ–¹"), Ok("æ±æ–¹".to_string()));
+        assert_eq!(unescape("\"\\u{6771}\\u{65b9}\""), Ok("東方".to_string()));
+        assert_eq!(unescape("東方"), Ok("東方".to_string()));
         assert_eq!(unescape("\"\\\\\"'\"\"'"), Ok("\\\"\"".to_string()));
         assert_eq!(unescape("'\"'"), Ok("\"".to_string()));
         assert_eq!(unescape("'\"'"), Ok("\"".to_string()));
@@ -320,10 +963,77 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_unescape_bytes_raw_hex() {
+        assert_eq!(unescape_bytes(b"\"\\xff\"").unwrap(), vec![0xff]);
+        assert_eq!(
+            unescape_bytes(b"\"foo\\xffbar\"").unwrap(),
+            b"foo\xffbar".to_vec()
+        );
+        assert!(unescape_bytes(b"\"\\xg0\"").is_err());
+        assert!(unescape_bytes(b"\"\\xf\"").is_err());
+    }
+
+    #[test]
+    fn test_unescape_error_offsets() {
+        assert_eq!(
+            unescape("\"abc\\q\""),
+            Err(UnescapeError::InvalidEscape {
+                offset: 4,
+                char: 'q'
+            })
+        );
+        assert_eq!(
+            unescape("\"abc\\"),
+            Err(UnescapeError::UnterminatedEscape { offset: 4 })
+        );
+        assert_eq!(
+            unescape("\"abc"),
+            Err(UnescapeError::UnterminatedQuote { offset: 0 })
+        );
+        assert_eq!(
+            unescape("\"\\u0041\""),
+            Err(UnescapeError::MissingOpeningBrace { offset: 1 })
+        );
+        assert_eq!(
+            unescape("\"\\u{1ffffffff}\""),
+            Err(UnescapeError::InvalidUnicodeEscape {
+                offset: 1,
+                digits: "1ffffffff".to_string()
+            })
+        );
+        assert_eq!(
+            unescape("\"\\u{110000}\""),
+            Err(UnescapeError::UnicodeOutOfRange {
+                offset: 1,
+                value: 0x110000
+            })
+        );
+    }
+
+    #[test]
+    fn test_unescape_invalid_utf8_and_multibyte_invalid_escape() {
+        // \xff alone isn't valid UTF-8, so unescape (unlike unescape_bytes) must report an error
+        // instead of panicking on the String::from_utf8 conversion.
+        assert_eq!(
+            unescape("\"\\xff\""),
+            Err(UnescapeError::InvalidUtf8 { offset: 0 })
+        );
+        // The character following an unrecognized escape may itself be multi-byte UTF-8; it must
+        // be decoded properly rather than reported as the mangled first byte of its encoding.
+        assert_eq!(
+            unescape("\"\\é\""),
+            Err(UnescapeError::InvalidEscape {
+                offset: 1,
+                char: 'é'
+            })
+        );
+    }
+
     #[test]
     fn test_round_trip() {
         let test_cases = vec![
-            "æ±æ–¹",
+            "東方",
             "foo bar baz",
             "\\",
             "\0",
@@ -336,9 +1046,79 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_round_trip_bytes() {
+        let test_cases: Vec<&[u8]> = vec![b"foo bar baz", b"\xff\xfe\x00", b"foo\xffbar", b"\"'"];
+
+        for case in test_cases {
+            assert_eq!(unescape_bytes(&escape_bytes(case)).unwrap(), case);
+        }
+    }
+
+    #[test]
+    fn test_split() {
+        assert_eq!(
+            split("'a b' c").unwrap(),
+            vec!["a b".to_string(), "c".to_string()]
+        );
+        assert_eq!(
+            split("  foo   bar  ").unwrap(),
+            vec!["foo".to_string(), "bar".to_string()]
+        );
+        assert_eq!(
+            split("'a b'c \"d\\\"e\"").unwrap(),
+            vec!["a bc".to_string(), "d\"e".to_string()]
+        );
+        assert_eq!(split("").unwrap(), Vec::<String>::new());
+        assert_eq!(
+            split("'unterminated"),
+            Err(UnescapeError::UnterminatedQuote { offset: 0 })
+        );
+    }
+
+    #[test]
+    fn test_split_error_offset_is_relative_to_whole_line() {
+        // The invalid escape is at byte 8 of the full line, not byte 4 of the second word; `split`
+        // must report the former, matching what `unescape` itself would report for the same line.
+        let line = "foo \"bad\\qxx\"";
+        assert_eq!(
+            split(line),
+            Err(UnescapeError::InvalidEscape {
+                offset: 8,
+                char: 'q'
+            })
+        );
+        assert_eq!(
+            unescape(&line[4..]),
+            Err(UnescapeError::InvalidEscape {
+                offset: 4,
+                char: 'q'
+            })
+        );
+    }
+
+    #[test]
+    fn test_join() {
+        assert_eq!(join(vec!["a b", "c"]), "'a b' c");
+        assert_eq!(join(vec!["foo", "bar"]), "foo bar");
+        assert_eq!(join(Vec::<&str>::new()), "");
+        assert_eq!(join(vec![""]), "''");
+        assert_eq!(join(vec!["a", "", "b"]), "a '' b");
+    }
+
+    #[test]
+    fn test_split_join_round_trip() {
+        let words = vec!["a b", "c", "\"quoted\"", "東方", ""];
+        assert_eq!(split(&join(words.clone())).unwrap(), words);
+    }
+
     quickcheck! {
         fn round_trips(s: String) -> bool {
             s == unescape(&escape(&s)).unwrap()
         }
+
+        fn round_trips_bytes(b: Vec<u8>) -> bool {
+            b == unescape_bytes(&escape_bytes(&b)).unwrap()
+        }
     }
 }